@@ -0,0 +1,71 @@
+//! a live egui window for dragging `Player`/`Patrol`/`FaceTarget` speeds and toggling
+//! enemy behaviors on and off, so tuning the movement constants doesn't require a
+//! recompile. Compiled in only under the `inspector` feature.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{FaceTarget, Patrol, Player};
+
+/// toggles for enabling/disabling individual enemy behavior systems at runtime
+pub struct BehaviorToggles {
+    pub face_target: bool,
+    pub patrol: bool,
+}
+
+impl Default for BehaviorToggles {
+    fn default() -> Self {
+        Self {
+            face_target: true,
+            patrol: true,
+        }
+    }
+}
+
+/// draws the tuning panel and writes any edits straight back into the tuned components.
+pub fn inspector_ui_system(
+    egui_context: ResMut<EguiContext>,
+    mut toggles: ResMut<BehaviorToggles>,
+    mut player_query: Query<&mut Player>,
+    mut face_target_query: Query<&mut FaceTarget>,
+    mut patrol_query: Query<&mut Patrol>,
+) {
+    egui::Window::new("Tuning").show(egui_context.ctx(), |ui| {
+        ui.heading("Player");
+        for mut player in player_query.iter_mut() {
+            ui.add(
+                egui::Slider::new(&mut player.movement_speed, 0.0..=1000.0)
+                    .text("movement speed"),
+            );
+            ui.add(
+                egui::Slider::new(&mut player.rotation_speed, 0.0..=std::f32::consts::TAU)
+                    .text("rotation speed"),
+            );
+        }
+
+        ui.separator();
+        ui.heading("Enemies");
+        ui.checkbox(&mut toggles.face_target, "face_target enabled");
+        for mut face_target in face_target_query.iter_mut() {
+            if face_target.snap {
+                continue;
+            }
+            ui.add(
+                egui::Slider::new(&mut face_target.rotation_speed, 0.0..=std::f32::consts::TAU)
+                    .text("face_target rotation speed"),
+            );
+        }
+
+        ui.checkbox(&mut toggles.patrol, "patrol enabled");
+        for mut patrol in patrol_query.iter_mut() {
+            ui.add(
+                egui::Slider::new(&mut patrol.movement_speed, 0.0..=1000.0)
+                    .text("patrol movement speed"),
+            );
+            ui.add(
+                egui::Slider::new(&mut patrol.rotation_speed, 0.0..=std::f32::consts::TAU)
+                    .text("patrol rotation speed"),
+            );
+        }
+    });
+}
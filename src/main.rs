@@ -1,11 +1,23 @@
+use std::ops::RangeInclusive;
+
 use bevy::{
     core::FixedTimestep,
     math::{const_vec2, Vec3Swizzles},
     prelude::*,
 };
+use rand::Rng;
+
+#[cfg(feature = "inspector")]
+mod inspector;
+#[cfg(feature = "particles")]
+mod particles;
 
 const TIME_STEP: f32 = 1.0 / 60.0;
 const BOUNDS: Vec2 = const_vec2!([1200.0, 640.0]);
+// how close a patrolling enemy needs to get to its wander target before picking a new one
+const PATROL_ARRIVAL_EPSILON: f32 = 4.0;
+// how far a locked-on target may wander before an enemy re-acquires the nearest player
+const TARGET_RANGE: f32 = 800.0;
 
 trait QuaternionEx {
     fn from_rotation_arc_2d(from: Vec2, to: Vec2) -> Quat;
@@ -37,18 +49,29 @@ impl QuaternionEx for Quat {
 }
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .add_startup_system(setup)
         .add_system_set(
             SystemSet::new()
                 .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(player_movement_system)
-                .with_system(snap_to_player_system)
-                .with_system(rotate_to_player_system),
+                .with_system(player_movement_system.label("steering"))
+                .with_system(face_target_system.label("steering"))
+                .with_system(patrol_system)
+                .with_system(integrate_motion_system.after("steering")),
         )
-        .add_system(bevy::input::system::exit_on_esc_system)
-        .run();
+        .add_system(bevy::input::system::exit_on_esc_system);
+
+    #[cfg(feature = "inspector")]
+    app.add_plugin(bevy_egui::EguiPlugin)
+        .init_resource::<inspector::BehaviorToggles>()
+        .add_system(inspector::inspector_ui_system);
+
+    #[cfg(feature = "particles")]
+    app.add_plugin(bevy_hanabi::HanabiPlugin)
+        .add_system(particles::setup_thruster_effect);
+
+    app.run();
 }
 
 /// player component
@@ -60,15 +83,43 @@ struct Player {
     rotation_speed: f32,
 }
 
-/// snap to player ship behavior
+/// turn to face the current target using the shortest rotation arc
 #[derive(Component)]
-struct SnapToPlayer;
+struct FaceTarget {
+    /// rotation speed in radians per second; ignored when `snap` is set
+    rotation_speed: f32,
+    /// snap immediately to the target instead of turning at `rotation_speed`
+    snap: bool,
+}
 
-/// rotate to face player ship behavior
+/// the player entity an enemy has locked onto, remembered between frames so it doesn't
+/// have to re-scan every player every tick
+#[derive(Component, Default)]
+struct Target(Option<Entity>);
+
+/// desired motion for an entity, applied to its `Transform` by `integrate_motion_system`
+#[derive(Component, Default)]
+struct Velocity {
+    /// linear velocity in pixels per second
+    linvel: Vec2,
+    /// angular velocity in radians per second around the Z axis
+    angvel: f32,
+}
+
+/// patrol/wander behavior: engages the nearest player inside the entity's
+/// patrol region, otherwise wanders between random points within it
 #[derive(Component)]
-struct RotateToPlayer {
+struct Patrol {
+    /// linear speed in pixels per second
+    movement_speed: f32,
     /// rotation speed in radians per second
     rotation_speed: f32,
+    /// horizontal extent of the patrol region
+    x_bounds: RangeInclusive<f32>,
+    /// vertical extent of the patrol region
+    y_bounds: RangeInclusive<f32>,
+    /// current wander destination, picked when no player is in range
+    patrol_target: Option<Vec2>,
 }
 
 fn setup(
@@ -94,7 +145,8 @@ fn setup(
         .insert(Player {
             movement_speed: 500.0,
             rotation_speed: f32::to_radians(360.0), // 360 degrees / second
-        });
+        })
+        .insert(Velocity::default());
 
     // snap to player enemy spawns on the left
     commands
@@ -103,26 +155,55 @@ fn setup(
             transform: Transform::from_xyz(0.0 - BOUNDS.x / 4.0, 0.0, 0.0),
             ..Default::default()
         })
-        .insert(SnapToPlayer);
+        .insert(FaceTarget {
+            rotation_speed: 0.0,
+            snap: true,
+        })
+        .insert(Target::default())
+        .insert(Velocity::default());
 
     // rotate to player enemy spawns on the right
     commands
         .spawn_bundle(SpriteBundle {
-            material: materials.add(enemy_handle.into()),
+            material: materials.add(enemy_handle.clone().into()),
             transform: Transform::from_xyz(0.0 + BOUNDS.x / 4.0, 0.0, 0.0),
             ..Default::default()
         })
-        .insert(RotateToPlayer {
+        .insert(FaceTarget {
             rotation_speed: f32::to_radians(45.0), // 45 degrees / second
-        });
+            snap: false,
+        })
+        .insert(Target::default())
+        .insert(Velocity::default());
+
+    // patrolling enemy wanders the top half of the level and engages players that stray into it
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(enemy_handle.into()),
+            transform: Transform::from_xyz(0.0, BOUNDS.y / 4.0, 0.0),
+            ..Default::default()
+        })
+        .insert(Patrol {
+            movement_speed: 200.0,
+            rotation_speed: f32::to_radians(90.0), // 90 degrees / second
+            x_bounds: -BOUNDS.x / 2.0..=BOUNDS.x / 2.0,
+            y_bounds: 0.0..=BOUNDS.y / 2.0,
+            patrol_target: None,
+        })
+        .insert(Velocity::default());
 }
 
-// demonstrates applying rotation and movement based on keyboard input.
+// demonstrates turning keyboard input into a desired velocity; `integrate_motion_system`
+// is what actually moves the ship.
 fn player_movement_system(
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&Player, &mut Transform)>,
+    mut query: Query<(&Player, &Transform, &mut Velocity)>,
+    #[cfg(feature = "particles")] mut thruster_query: Query<
+        &mut bevy_hanabi::ParticleEffect,
+        With<particles::Thruster>,
+    >,
 ) {
-    let (ship, mut transform) = query.single_mut();
+    let (ship, transform, mut velocity) = query.single_mut();
 
     let mut rotation_factor = 0.0;
     let mut movement_factor = 0.0;
@@ -139,81 +220,190 @@ fn player_movement_system(
         movement_factor += 1.0;
     }
 
-    // create the change in rotation around the Z axis (pointing through the 2d plane of the screen)
-    let rotation_delta = Quat::from_rotation_z(rotation_factor * ship.rotation_speed * TIME_STEP);
-    // update the ship rotation with our rotation delta
-    transform.rotation *= rotation_delta;
+    velocity.angvel = rotation_factor * ship.rotation_speed;
 
     // get the ship's forward vector by applying the current rotation to the ships initial facing vector
-    let movement_direction = transform.rotation * Vec3::Y;
-    // get the distance the ship will move based on direction, the ship's movement speed and delta time
-    let movement_distance = movement_factor * ship.movement_speed * TIME_STEP;
-    // create the change in translation using the new movement direction and distance
-    let translation_delta = movement_direction * movement_distance;
-    // update the ship translation with our new translation delta
-    transform.translation += translation_delta;
-
-    // bound the ship within the invisible level bounds
+    let movement_direction = (transform.rotation * Vec3::Y).xy();
+    velocity.linvel = movement_direction * movement_factor * ship.movement_speed;
+
+    #[cfg(feature = "particles")]
+    particles::update_thruster(movement_factor, &mut thruster_query);
+}
+
+// advances every `Transform` from its `Velocity`, clamping translation within the level
+// bounds and zeroing linear velocity on any axis that hits a bound so ships slide along
+// walls instead of sticking.
+fn integrate_motion_system(mut query: Query<(&mut Velocity, &mut Transform)>) {
     let extents = Vec3::from((BOUNDS / 2.0, 0.0));
-    transform.translation = transform.translation.min(extents).max(-extents);
+
+    for (mut velocity, mut transform) in query.iter_mut() {
+        transform.rotation *= Quat::from_rotation_z(velocity.angvel * TIME_STEP);
+        transform.translation += Vec3::from((velocity.linvel * TIME_STEP, 0.0));
+
+        let clamped = transform.translation.min(extents).max(-extents);
+        if clamped.x != transform.translation.x {
+            velocity.linvel.x = 0.0;
+        }
+        if clamped.y != transform.translation.y {
+            velocity.linvel.y = 0.0;
+        }
+        transform.translation = clamped;
+    }
 }
 
-// demonstrates rotating an enemy ship to face the player ship at a given rotation speed.
-fn rotate_to_player_system(
-    mut query: Query<(&RotateToPlayer, &mut Transform), Without<Player>>,
-    player_query: Query<&Transform, With<Player>>,
+// finds the player closest to `position` (smallest squared XY distance).
+fn nearest_player(
+    position: Vec2,
+    player_query: &Query<(Entity, &Transform), With<Player>>,
+) -> Option<Entity> {
+    player_query
+        .iter()
+        .min_by(|(_, a), (_, b)| {
+            let dist_a = (a.translation.xy() - position).length_squared();
+            let dist_b = (b.translation.xy() - position).length_squared();
+            dist_a.partial_cmp(&dist_b).unwrap()
+        })
+        .map(|(entity, _)| entity)
+}
+
+// re-acquires `target` with the nearest player if it has died or wandered outside
+// `TARGET_RANGE`, leaving it untouched otherwise.
+fn acquire_target(
+    target: &mut Target,
+    position: Vec2,
+    player_query: &Query<(Entity, &Transform), With<Player>>,
 ) {
-    let player_transform = player_query.single();
+    let in_range = target.0.and_then(|entity| player_query.get(entity).ok()).map_or(
+        false,
+        |(_, player_transform)| {
+            (player_transform.translation.xy() - position).length_squared()
+                <= TARGET_RANGE * TARGET_RANGE
+        },
+    );
+
+    if !in_range {
+        target.0 = nearest_player(position, player_query);
+    }
+}
 
-    for (config, mut enemy_transform) in query.iter_mut() {
-        let enemy_side = (enemy_transform.rotation * -Vec3::X).xy();
-        let to_player =
-            (player_transform.translation.xy() - enemy_transform.translation.xy()).normalize();
+// demonstrates turning an enemy ship to face its acquired target along the shortest
+// rotation arc, either over time at `rotation_speed` or immediately when `snap` is set.
+fn face_target_system(
+    #[cfg(feature = "inspector")] toggles: Res<inspector::BehaviorToggles>,
+    mut query: Query<(&FaceTarget, &mut Target, &Transform, &mut Velocity), Without<Player>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+) {
+    #[cfg(feature = "inspector")]
+    if !toggles.face_target {
+        // stop any turn in progress so a disabled behavior leaves the enemy still
+        for (_, _, _, mut velocity) in query.iter_mut() {
+            velocity.angvel = 0.0;
+        }
+        return;
+    }
 
-        let side_dot_player = enemy_side.dot(to_player);
-        let rotation_factor = if side_dot_player > f32::EPSILON {
-            1.0
-        } else if side_dot_player < -f32::EPSILON {
-            -1.0
-        } else {
-            // already facing the player
-            continue;
+    for (config, mut target, enemy_transform, mut velocity) in query.iter_mut() {
+        acquire_target(&mut target, enemy_transform.translation.xy(), &player_query);
+        let player_transform = match target.0.and_then(|entity| player_query.get(entity).ok()) {
+            Some((_, transform)) => transform,
+            None => {
+                velocity.angvel = 0.0;
+                continue;
+            }
         };
 
-        // limit rotation so we don't overshoot the target
         let enemy_forward = (enemy_transform.rotation * Vec3::Y).xy();
-        let forward_dot_player = enemy_forward.dot(to_player);
-        let max_angle = forward_dot_player.min(1.0).max(-1.0).acos(); // clamp acos for safety
-
-        // calculate angle of rotation with limit
-        let rotation_angle = rotation_factor * (config.rotation_speed * TIME_STEP).min(max_angle);
+        let to_player =
+            (player_transform.translation.xy() - enemy_transform.translation.xy()).normalize();
 
-        // get the quaternion to rotate from the current enemy facing direction towards the
-        // direction facing the player
-        let rotation_delta = Quat::from_rotation_z(rotation_angle);
+        // get the full shortest-arc rotation towards the player and extract its signed
+        // angle around Z; this gives the correct turn direction in one step, unlike the
+        // side-dot-product + acos approach it replaces, which loses the sign through acos
+        let rotation_arc = Quat::from_rotation_arc_2d(enemy_forward, to_player);
+        let angle = 2.0 * rotation_arc.z.atan2(rotation_arc.w);
 
-        // rotate the enemy to face the player
-        enemy_transform.rotation *= rotation_delta;
+        velocity.angvel = if config.snap {
+            // cover the whole arc in a single tick
+            angle / TIME_STEP
+        } else {
+            angle.clamp(-config.rotation_speed * TIME_STEP, config.rotation_speed * TIME_STEP)
+                / TIME_STEP
+        };
     }
 }
 
-// demonstrates snapping the enemy ship to face the player ship immediately.
-fn snap_to_player_system(
-    mut query: Query<&mut Transform, (With<SnapToPlayer>, Without<Player>)>,
+// demonstrates an enemy that engages the nearest player inside its patrol region and
+// otherwise wanders between random points within it.
+fn patrol_system(
+    #[cfg(feature = "inspector")] toggles: Res<inspector::BehaviorToggles>,
+    mut query: Query<(&mut Patrol, &Transform, &mut Velocity), Without<Player>>,
     player_query: Query<&Transform, With<Player>>,
 ) {
-    let player_transform = player_query.single();
+    #[cfg(feature = "inspector")]
+    if !toggles.patrol {
+        // stop any motion in progress so a disabled behavior leaves the enemy still
+        for (_, _, mut velocity) in query.iter_mut() {
+            velocity.linvel = Vec2::ZERO;
+            velocity.angvel = 0.0;
+        }
+        return;
+    }
 
-    for mut enemy_transform in query.iter_mut() {
-        let enemy_forward = (enemy_transform.rotation * Vec3::Y).xy();
-        let to_player =
-            (player_transform.translation.xy() - enemy_transform.translation.xy()).normalize();
+    for (mut patrol, transform, mut velocity) in query.iter_mut() {
+        let position = transform.translation.xy();
+
+        // find the nearest player whose position lies inside this entity's patrol bounds,
+        // using squared distance to avoid a sqrt
+        let nearest_player = player_query
+            .iter()
+            .map(|player_transform| player_transform.translation.xy())
+            .filter(|player_pos| {
+                patrol.x_bounds.contains(&player_pos.x) && patrol.y_bounds.contains(&player_pos.y)
+            })
+            .min_by(|a, b| {
+                let dist_a = (*a - position).length_squared();
+                let dist_b = (*b - position).length_squared();
+                dist_a.partial_cmp(&dist_b).unwrap()
+            });
+
+        let target = if let Some(player_pos) = nearest_player {
+            // engage the player directly; forget any wander target so we pick a fresh one
+            // once it's out of range again
+            patrol.patrol_target = None;
+            player_pos
+        } else {
+            match patrol.patrol_target {
+                Some(target)
+                    if (target - position).length_squared()
+                        > PATROL_ARRIVAL_EPSILON * PATROL_ARRIVAL_EPSILON =>
+                {
+                    target
+                }
+                _ => {
+                    let mut rng = rand::thread_rng();
+                    let target = Vec2::new(
+                        rng.gen_range(patrol.x_bounds.clone()),
+                        rng.gen_range(patrol.y_bounds.clone()),
+                    );
+                    patrol.patrol_target = Some(target);
+                    target
+                }
+            }
+        };
 
-        // get the quaternion to rotate from the current enemy facing direction to the direction
-        // facing the player
-        let rotate_to_player = Quat::from_rotation_arc_2d(enemy_forward, to_player);
+        let enemy_forward = (transform.rotation * Vec3::Y).xy();
+        let to_target = (target - position).normalize_or_zero();
+        velocity.angvel = if to_target == Vec2::ZERO {
+            0.0
+        } else {
+            // get the full shortest-arc rotation towards the target, then limit its
+            // magnitude so we don't overshoot
+            let rotate_to_target = Quat::from_rotation_arc_2d(enemy_forward, to_target);
+            let full_angle = 2.0 * rotate_to_target.z.atan2(rotate_to_target.w);
+            full_angle.clamp(-patrol.rotation_speed * TIME_STEP, patrol.rotation_speed * TIME_STEP)
+                / TIME_STEP
+        };
 
-        // rotate the enemy to face the player
-        enemy_transform.rotation *= rotate_to_player;
+        velocity.linvel = (transform.rotation * Vec3::Y).xy() * patrol.movement_speed;
     }
 }
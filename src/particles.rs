@@ -0,0 +1,74 @@
+//! spawns a `bevy_hanabi` exhaust burst trailing behind the player ship, with emission
+//! turned on only while the ship is accelerating. Compiled in only under the `particles`
+//! feature.
+
+use bevy::prelude::*;
+use bevy_hanabi::prelude::*;
+
+use crate::Player;
+
+// how many particles per second of movement speed the thruster emits while accelerating
+const EMISSION_RATE_PER_SPEED: f32 = 0.5;
+
+/// marks the child entity carrying the ship's thruster `ParticleEffect`, positioned at the
+/// rear of the ship along its backward vector.
+#[derive(Component)]
+pub struct Thruster;
+
+pub fn setup_thruster_effect(
+    mut commands: Commands,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    player_query: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (player_entity, player) in player_query.iter() {
+        let mut gradient = Gradient::new();
+        gradient.add_key(0.0, Vec4::new(1.0, 0.8, 0.3, 1.0));
+        gradient.add_key(1.0, Vec4::new(1.0, 0.3, 0.0, 0.0));
+
+        // `bevy_hanabi::Spawner` only exposes `set_active`/`is_active`/`reset` at runtime,
+        // not a rate setter, so the rate has to be baked in at spawn time rather than
+        // varied per-tick with `movement_speed`
+        let rate = player.movement_speed * EMISSION_RATE_PER_SPEED;
+        let effect = effects.add(
+            EffectAsset {
+                name: "thruster".to_string(),
+                capacity: 256,
+                spawner: Spawner::rate(rate.into()),
+                ..Default::default()
+            }
+            .init(PositionSphereModifier {
+                radius: 2.0,
+                speed: 40.0.into(),
+                dimension: ShapeDimension::Volume,
+                ..Default::default()
+            })
+            .render(ColorOverLifetimeModifier { gradient }),
+        );
+
+        let thruster_entity = commands
+            .spawn_bundle(ParticleEffectBundle {
+                effect: ParticleEffect::new(effect),
+                // positioned at the rear of the ship; as a child its world position and
+                // rotation follow the ship's backward vector automatically
+                transform: Transform::from_translation(-Vec3::Y * 16.0),
+                ..Default::default()
+            })
+            .insert(Thruster)
+            .id();
+
+        commands.entity(player_entity).add_child(thruster_entity);
+    }
+}
+
+/// activates the thruster only while `movement_factor` is positive, i.e. while the player
+/// is accelerating.
+pub fn update_thruster(
+    movement_factor: f32,
+    thruster_query: &mut Query<&mut ParticleEffect, With<Thruster>>,
+) {
+    for mut effect in thruster_query.iter_mut() {
+        if let Some(spawner) = effect.maybe_spawner() {
+            spawner.set_active(movement_factor > 0.0);
+        }
+    }
+}